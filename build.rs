@@ -0,0 +1,116 @@
+//! Build-time embedded grammar support.
+//!
+//! When the `embedded-grammars` feature is enabled, this clones each built-in
+//! grammar's repository, checks out its pinned revision, compiles its `parser.c`,
+//! and emits a generated module (see `src/embedded.rs`) mapping each
+//! `TargetLanguage` to a statically linked `tree_sitter::Language` and its
+//! highlight query source. This lets a published binary ship with grammars baked
+//! in, with no `git2` calls, writable cache directory, or network access needed
+//! at runtime.
+
+#[cfg(feature = "embedded-grammars")]
+fn main() -> anyhow::Result<()> {
+    build::run()
+}
+
+#[cfg(not(feature = "embedded-grammars"))]
+fn main() {}
+
+#[cfg(feature = "embedded-grammars")]
+mod build {
+    use anyhow::Result;
+    use std::fmt::Write as _;
+    use std::path::PathBuf;
+
+    // Pull in the same `BUILTIN_GRAMMARS` table that `TargetLanguage::git_repo`/
+    // `revision`/`subpath` read from at runtime, so this build step can't silently
+    // drift from the non-embedded path.
+    include!(concat!(env!("CARGO_MANIFEST_DIR"), "/src/builtin_grammars.rs"));
+
+    pub fn run() -> Result<()> {
+        let out_dir = PathBuf::from(std::env::var("OUT_DIR")?);
+        let mut registry = String::new();
+        writeln!(registry, "// @generated by build.rs, do not edit by hand")?;
+
+        // A grammar's exported symbol follows its repo name, not the variant name
+        // (`Shell` is tree-sitter-bash, which exports `tree_sitter_bash`)
+        let symbol = |remote: &str| {
+            let repo = remote.rsplit('/').next().unwrap_or(remote);
+            repo.trim_end_matches(".git").replace('-', "_")
+        };
+
+        for (_, remote, _, _) in BUILTIN_GRAMMARS {
+            writeln!(
+                registry,
+                "unsafe extern \"C\" {{ fn {}() -> tree_sitter::Language; }}",
+                symbol(remote)
+            )?;
+        }
+
+        writeln!(
+            registry,
+            "pub fn embedded_language(lang: crate::TargetLanguage) -> Option<(tree_sitter::Language, &'static str)> {{"
+        )?;
+        writeln!(registry, "    match lang {{")?;
+
+        for (variant, remote, revision, subpath) in BUILTIN_GRAMMARS {
+            let repo_path = out_dir.join(variant);
+            if !repo_path.exists() {
+                git2::Repository::clone(remote, &repo_path)?;
+            }
+            let repo = git2::Repository::open(&repo_path)?;
+            let oid = git2::Oid::from_str(revision)?;
+            let commit = repo.find_commit(oid)?;
+            repo.set_head_detached(commit.id())?;
+            let mut checkout_builder = git2::build::CheckoutBuilder::new();
+            checkout_builder.force();
+            repo.checkout_head(Some(&mut checkout_builder))?;
+
+            let grammar_dir = match subpath {
+                Some(subpath) => repo_path.join(subpath),
+                None => repo_path.clone(),
+            };
+
+            let src_dir = grammar_dir.join("src");
+            let mut build = cc::Build::new();
+            build
+                .include(&src_dir)
+                .file(src_dir.join("parser.c"))
+                .warnings(false);
+
+            // Some grammars (tree-sitter-bash, tree-sitter-html, tree-sitter-yaml, ...)
+            // split custom scanning logic for conflicts/externals out into an external
+            // scanner that `parser.c` calls into, which `tree_sitter_loader` also
+            // compiles alongside `parser.c` for the non-embedded path
+            let scanner_c = src_dir.join("scanner.c");
+            let scanner_cc = src_dir.join("scanner.cc");
+            if scanner_cc.exists() {
+                build.cpp(true).file(scanner_cc);
+            } else if scanner_c.exists() {
+                build.file(scanner_c);
+            }
+
+            build.compile(&format!("tree-sitter-{}", variant.to_lowercase()));
+
+            let highlights_path = grammar_dir.join("queries").join("highlights.scm");
+            writeln!(
+                registry,
+                "        crate::TargetLanguage::{variant} => Some((unsafe {{ {}() }}, include_str!({:?}))),",
+                symbol(remote),
+                highlights_path,
+            )?;
+
+            println!("cargo:rerun-if-changed={}", highlights_path.display());
+        }
+
+        writeln!(registry, "        _ => None,")?;
+        writeln!(registry, "    }}")?;
+        writeln!(registry, "}}")?;
+
+        std::fs::write(out_dir.join("embedded_grammars.rs"), registry)?;
+        println!("cargo:rerun-if-changed=build.rs");
+        println!("cargo:rerun-if-changed=src/builtin_grammars.rs");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,64 @@
+// Canonical table of built-in grammar sources: (`TargetLanguage` variant name, git
+// remote, pinned revision, subpath). This is the single source of truth consumed by
+// both `crate::TargetLanguage`'s `git_repo`/`revision`/`subpath` methods and, via
+// `include!`, the `embedded-grammars` step in `build.rs` — so bumping a pinned
+// revision here updates both build modes together instead of silently diverging.
+// (Plain comments rather than `//!`: inner doc comments can't be `include!`d.)
+pub const BUILTIN_GRAMMARS: &[(&str, &str, &str, Option<&str>)] = &[
+    (
+        "Rust",
+        "https://github.com/tree-sitter/tree-sitter-rust.git",
+        "eaf06de2949d70d328d580541dbfca02c1ed24ed",
+        None,
+    ),
+    (
+        "Python",
+        "https://github.com/tree-sitter/tree-sitter-python.git",
+        "4bfdd9033a2225cc95032ce77066b7aeca9e2efc",
+        None,
+    ),
+    (
+        "Json",
+        "https://github.com/tree-sitter/tree-sitter-json.git",
+        "40a81c01a40ac48744e0c8ccabbaba1920441199",
+        None,
+    ),
+    (
+        "Yaml",
+        "https://github.com/tree-sitter-grammars/tree-sitter-yaml.git",
+        "f0502c67a6da901a164b04d7e0a7dbd2e85e46e6",
+        None,
+    ),
+    (
+        "Toml",
+        "https://github.com/ikatyang/tree-sitter-toml.git",
+        "7c26764b6897acb08ddf0271476eb75e5444aa54",
+        None,
+    ),
+    (
+        "Html",
+        "https://github.com/tree-sitter/tree-sitter-html.git",
+        "4b2a5f01c2ca9c1b2dfdd2faefc2b5a28a843aa2",
+        None,
+    ),
+    (
+        "Javascript",
+        "https://github.com/tree-sitter/tree-sitter-javascript.git",
+        "f772967f7b7bc7c28f845be2420a38472b16a8e",
+        None,
+    ),
+    (
+        "Shell",
+        "https://github.com/tree-sitter/tree-sitter-bash.git",
+        "2580804ce8734677290f1745354bf3ba1cd16620",
+        None,
+    ),
+];
+
+/// Look up a grammar's (remote, revision, subpath) by its `TargetLanguage` variant name
+pub fn lookup(variant: &str) -> Option<(&'static str, &'static str, Option<&'static str>)> {
+    BUILTIN_GRAMMARS
+        .iter()
+        .find(|(name, ..)| *name == variant)
+        .map(|(_, remote, revision, subpath)| (*remote, *revision, *subpath))
+}
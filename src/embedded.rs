@@ -0,0 +1,8 @@
+//! Runtime side of the build-time embedded grammar mode. When the
+//! `embedded-grammars` feature is enabled, `build.rs` clones, checks out, and
+//! compiles each built-in grammar's pinned revision at compile time and emits a
+//! generated `embedded_language` function (included below) mapping each
+//! [`crate::TargetLanguage`] to its statically linked [`tree_sitter::Language`] and
+//! highlight query source.
+
+include!(concat!(env!("OUT_DIR"), "/embedded_grammars.rs"));
@@ -8,4 +8,6 @@ pub enum HtmlRenderingError {
     SharedLibDoesntExist,
     #[error("Language is not imported")]
     LanguageParserNotImplemented,
+    #[error("Grammar remote {0:?} has no final path segment to derive a repo name from")]
+    InvalidGrammarRemote(String),
 }
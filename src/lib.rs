@@ -14,7 +14,11 @@
 //! let lang = TargetLanguage::Python;
 //! let html = render_html(my_pyblock, lang);
 //! ```
+mod builtin_grammars;
+#[cfg(feature = "embedded-grammars")]
+mod embedded;
 mod error;
+mod registry;
 mod target_language;
 
 use anyhow::Result;
@@ -24,17 +28,102 @@ use tree_sitter_highlight::HighlightConfiguration;
 use tree_sitter_highlight::{Highlighter, HtmlRenderer};
 use tree_sitter_loader::Loader;
 
+pub use registry::{GrammarDescriptor, GrammarRegistry};
 pub use target_language::TargetLanguage;
 
+/// Platform-specific extension for compiled tree-sitter parser dynamic libraries
+#[cfg(target_os = "macos")]
+pub const DYLIB_EXTENSION: &str = "dylib";
+#[cfg(target_os = "windows")]
+pub const DYLIB_EXTENSION: &str = "dll";
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+pub const DYLIB_EXTENSION: &str = "so";
+
+/// Resolve the base cache directory we clone grammar repos and compile parsers into,
+/// portably across Linux, macOS, and Windows
+fn cache_dir() -> Result<std::path::PathBuf> {
+    use etcetera::{app_strategy::AppStrategyArgs, choose_app_strategy, AppStrategy};
+
+    let strategy = choose_app_strategy(AppStrategyArgs {
+        top_level_domain: "dev".into(),
+        author: "highlight-ez".into(),
+        app_name: "tree-sitter".into(),
+    })?;
+    Ok(strategy.cache_dir())
+}
+
+/// Options controlling how [`render_html_with`] renders a highlighted code block
+pub struct RenderOptions {
+    /// Theme to pull highlight colors from; defaults to the user's tree-sitter CLI
+    /// config when `None`
+    pub theme: Option<Theme>,
+    /// Emit inline `style="..."` attributes instead of `class="hl-..."` names, so the
+    /// output is self-contained and doesn't depend on an external stylesheet
+    pub inline_styles: bool,
+    /// Whether to render the line-number column
+    pub show_line_numbers: bool,
+    /// The line number the first line of the code block should be labeled with
+    pub starting_line: usize,
+    /// Line numbers that get an extra `emphasized-line` CSS class on their `<tr>`
+    pub emphasized_lines: std::collections::HashSet<usize>,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            theme: None,
+            inline_styles: false,
+            show_line_numbers: true,
+            starting_line: 1,
+            emphasized_lines: std::collections::HashSet::new(),
+        }
+    }
+}
+
+/// Compute the `<span>` attribute for a highlight: an inline `style="..."` pulled from
+/// the theme when `inline_styles` is set, otherwise a stable `class="hl-<name>"`
+fn highlight_attr(inline_styles: bool, css: Option<&str>, name: &str) -> String {
+    if inline_styles {
+        css.map(str::to_string).unwrap_or_default()
+    } else {
+        format!("class=\"hl-{name}\"")
+    }
+}
+
+/// Render already-highlighted lines into an HTML `<table>`, honoring the
+/// line-number, starting-line, and emphasized-line options
+fn render_table<T: std::fmt::Display>(
+    lines: impl Iterator<Item = T>,
+    options: &RenderOptions,
+    out_str: &mut String,
+) -> Result<()> {
+    writeln!(out_str, "<table>")?;
+    for (i, line) in lines.enumerate() {
+        let line_number = options.starting_line + i;
+        let row_class = if options.emphasized_lines.contains(&line_number) {
+            " class=emphasized-line"
+        } else {
+            ""
+        };
+
+        write!(out_str, "<tr{row_class}>")?;
+        if options.show_line_numbers {
+            write!(out_str, "<td class=line-number>{line_number}</td>")?;
+        }
+        writeln!(out_str, "<td class=line>{line}</td></tr>")?;
+    }
+    writeln!(out_str, "</table>")?;
+
+    Ok(())
+}
+
 /// Take generated arguments and make the calls to tree-sitter
 fn string_html(
     loader: &Loader,
     theme: &Theme,
     source: &[u8],
     config: &HighlightConfiguration,
-    _quiet: bool,
-    _print_time: bool,
-    _cancellation_flag: Option<&usize>,
+    options: &RenderOptions,
 ) -> Result<String> {
     let mut highlighter = Highlighter::new();
 
@@ -44,32 +133,45 @@ fn string_html(
         loader.highlight_config_for_injection_string(string)
     })?;
 
+    // `highlight.0` indexes the theme's `highlight_names` the configuration was set up
+    // with, not the query's capture names
+    let attrs: Vec<String> = theme
+        .highlight_names
+        .iter()
+        .zip(&theme.styles)
+        .map(|(name, style)| highlight_attr(options.inline_styles, style.css.as_deref(), name))
+        .collect();
+
     let mut renderer = HtmlRenderer::new();
     renderer.render(events, source, &move |highlight| {
-        theme.styles[highlight.0]
-            .css
-            .as_ref()
-            .map_or_else(|| "".as_bytes(), |css_style| css_style.as_bytes())
+        attrs[highlight.0].as_bytes()
     })?;
 
-    writeln!(&mut out_str, "<table>")?;
-    for (i, line) in renderer.lines().enumerate() {
-        writeln!(
-            &mut out_str,
-            "<tr><td class=line-number>{}</td><td class=line>{line}</td></tr>",
-            i + 1,
-        )?;
-    }
-
-    writeln!(&mut out_str, "</table>")?;
+    render_table(renderer.lines(), options, &mut out_str)?;
 
     Ok(out_str)
 }
 
-/// Render the code block into HTML, using the styling defaulted to by tree-sitter
-pub fn render_html(code_block: &str, lang: TargetLanguage) -> Result<String> {
-    if check_for_parser(lang).is_err() {
-        generate_parser(lang)?;
+/// Resolve a language identifier against the built-in [`TargetLanguage`] enum first,
+/// falling back to a `registry` of custom grammars if the identifier isn't a built-in
+fn resolve_grammar(lang_id: &str, registry: Option<&GrammarRegistry>) -> Result<GrammarDescriptor> {
+    if let Ok(lang) = lang_id.parse::<TargetLanguage>() {
+        return lang.descriptor();
+    }
+    if let Some(descriptor) = registry.and_then(|registry| registry.resolve(lang_id)) {
+        return Ok(descriptor.clone());
+    }
+    Err(error::HtmlRenderingError::LanguageParserNotImplemented.into())
+}
+
+/// Render the code block into HTML for an already-resolved grammar
+fn render_descriptor_with(
+    code_block: &str,
+    descriptor: &GrammarDescriptor,
+    options: &RenderOptions,
+) -> Result<String> {
+    if check_for_parser_descriptor(descriptor).is_err() {
+        generate_parser_for(descriptor)?;
     }
     // FROM tree-sitter-cli
     let mut loader = tree_sitter_loader::Loader::new().unwrap();
@@ -79,8 +181,8 @@ pub fn render_html(code_block: &str, lang: TargetLanguage) -> Result<String> {
     let loader_config = config.get().unwrap();
     loader.find_all_languages(&loader_config).unwrap();
 
-    let extension = match lang.extension() {
-        Some(e) => e,
+    let extension = match descriptor.extensions.first() {
+        Some(e) => e.as_str(),
         None => return Err(error::HtmlRenderingError::LanguageParserNotImplemented.into()),
     };
 
@@ -98,61 +200,151 @@ pub fn render_html(code_block: &str, lang: TargetLanguage) -> Result<String> {
         .unwrap()
         .unwrap();
 
+    let theme = options.theme.as_ref().unwrap_or(&theme_config.theme);
     let source = code_block.as_bytes();
-    string_html(
-        &loader,
-        &theme_config.theme,
-        &source,
-        highlight_config,
-        false,
-        false,
-        None,
-    )
+    string_html(&loader, theme, &source, highlight_config, options)
 }
 
-/// Generate a parser for the language by calling tree-sitter
-#[cfg(target_os = "linux")]
-pub fn generate_parser(lang: TargetLanguage) -> Result<()> {
+/// Render the code block into HTML for an already-resolved grammar, using the styling
+/// defaulted to by tree-sitter
+fn render_descriptor(code_block: &str, descriptor: &GrammarDescriptor) -> Result<String> {
+    render_descriptor_with(code_block, descriptor, &RenderOptions::default())
+}
+
+/// Render the code block into HTML for a grammar statically linked in by the
+/// `embedded-grammars` build script, skipping the cache/loader machinery entirely
+#[cfg(feature = "embedded-grammars")]
+fn render_embedded(
+    code_block: &str,
+    language: tree_sitter::Language,
+    highlights_query: &str,
+    options: &RenderOptions,
+) -> Result<String> {
+    let loader = tree_sitter_loader::Loader::new().unwrap();
+    let config = tree_sitter_config::Config::load(None).unwrap();
+    let theme_config: tree_sitter_cli::highlight::ThemeConfig = config.get().unwrap();
+
+    let mut highlight_config =
+        HighlightConfiguration::new(language, "embedded", highlights_query, "", "")?;
+    highlight_config.configure(&theme_config.theme.highlight_names);
+
+    let theme = options.theme.as_ref().unwrap_or(&theme_config.theme);
+    let source = code_block.as_bytes();
+    string_html(&loader, theme, source, &highlight_config, options)
+}
+
+/// Render the code block into HTML, using the styling defaulted to by tree-sitter
+pub fn render_html(code_block: &str, lang: TargetLanguage) -> Result<String> {
+    render_html_with(code_block, lang, &RenderOptions::default())
+}
+
+/// Render the code block into HTML with full control over theme and output markup via
+/// [`RenderOptions`] — e.g. to produce standalone, self-styled HTML for email or
+/// static-site embedding without depending on an external stylesheet
+pub fn render_html_with(
+    code_block: &str,
+    lang: TargetLanguage,
+    options: &RenderOptions,
+) -> Result<String> {
+    #[cfg(feature = "embedded-grammars")]
+    if let Some((language, highlights_query)) = embedded::embedded_language(lang) {
+        return render_embedded(code_block, language, highlights_query, options);
+    }
+
+    render_descriptor_with(code_block, &lang.descriptor()?, options)
+}
+
+/// Render the code block into HTML, resolving `lang_id` against the built-in
+/// [`TargetLanguage`] enum first and then, if given, against a [`GrammarRegistry`] of
+/// custom grammars. This is the entry point for highlighting languages that aren't
+/// in the built-in enum.
+pub fn render_html_named(
+    code_block: &str,
+    lang_id: &str,
+    registry: Option<&GrammarRegistry>,
+) -> Result<String> {
+    render_descriptor(code_block, &resolve_grammar(lang_id, registry)?)
+}
+
+/// Resolve the commit a grammar repo should be checked out to: the descriptor's pinned
+/// revision if it has one, otherwise the tip of `origin`'s currently checked-out branch
+fn target_commit(repo: &git2::Repository, descriptor: &GrammarDescriptor) -> Result<git2::Oid> {
+    if let Some(revision) = &descriptor.revision {
+        return Ok(git2::Oid::from_str(revision)?);
+    }
+    let head = repo.head()?;
+    let branch_name = head
+        .shorthand()
+        .ok_or(error::HtmlRenderingError::SharedLibDoesntExist)?;
+    let remote_ref = repo.find_reference(&format!("refs/remotes/origin/{branch_name}"))?;
+    Ok(remote_ref.peel_to_commit()?.id())
+}
+
+/// Fetch and compile the parser for an already-resolved grammar descriptor
+fn generate_parser_for(descriptor: &GrammarDescriptor) -> Result<()> {
     /*
     Source for ABI version: https://github.com/tree-sitter/tree-sitter/blob/master/cli/src/main.rs
     */
     const ABI_VERSION: usize = 14;
     let current_dir = std::path::PathBuf::from(std::env::current_dir()?);
-    let home_path = match std::env::home_dir() {
-        Some(p) => p,
-        None => return Err(error::HtmlRenderingError::SharedLibDoesntExist.into()),
-    };
-    log::trace!("found home path {:?}", home_path);
+    let base_cache_path = cache_dir()?;
+    log::trace!("found cache base path {:?}", base_cache_path);
 
-    let git_url = match lang.git_repo() {
-        Some(n) => n,
-        None => return Err(error::HtmlRenderingError::LanguageParserNotImplemented.into()),
-    };
-    let cache_path = home_path.join(".cache").join("tree-sitter").join("lib");
-    let soname = match lang.soname() {
-        Some(s) => s,
-        None => return Err(error::HtmlRenderingError::SharedLibDoesntExist.into()),
-    };
-    let sopath = cache_path.join(soname);
+    let cache_path = base_cache_path.join("lib");
+    let sopath = cache_path.join(format!("{}.{DYLIB_EXTENSION}", descriptor.soname));
+    let marker_path = cache_path.join(format!("{}.rev", descriptor.soname));
     log::trace!("found sopath path {:?}", sopath);
-    let repo_name = std::path::Path::new(git_url)
+
+    if let Some(revision) = &descriptor.revision {
+        if marker_path.exists() && std::fs::read_to_string(&marker_path)?.trim() == revision {
+            log::debug!(
+                "Parser for {:?} is already built at pinned revision {}, skipping",
+                descriptor.name,
+                revision
+            );
+            return Ok(());
+        }
+    }
+
+    let repo_name = std::path::Path::new(&descriptor.remote)
         .file_name()
-        .unwrap()
+        .ok_or_else(|| {
+            error::HtmlRenderingError::InvalidGrammarRemote(descriptor.remote.clone())
+        })?
         .to_string_lossy();
     let repo_name = repo_name.split(".").next().unwrap();
 
-    let repo_path = home_path
-        .join(".cache")
-        .join("tree-sitter")
-        .join("parsers")
-        .join(repo_name);
+    let repo_path = base_cache_path.join("parsers").join(repo_name);
 
-    if !repo_path.exists() {
-        log::debug!("Cloning Git repo {:?} to path {:?}", git_url, repo_path);
-        git2::Repository::clone(git_url, repo_path.clone())?;
-    }
+    let repo = if !repo_path.exists() {
+        log::debug!(
+            "Cloning Git repo {:?} to path {:?}",
+            descriptor.remote,
+            repo_path
+        );
+        git2::Repository::clone(&descriptor.remote, repo_path.clone())?
+    } else {
+        log::debug!("Repo already cloned at {:?}, fetching updates", repo_path);
+        let repo = git2::Repository::open(&repo_path)?;
+        let mut remote = repo.find_remote("origin")?;
+        remote.fetch(&["+refs/heads/*:refs/remotes/origin/*"], None, None)?;
+        repo
+    };
 
-    let grammar_path = repo_path.join("grammar.js");
+    let target_oid = target_commit(&repo, descriptor)?;
+    log::debug!("Checking out {:?} at {}", repo_path, target_oid);
+    let commit = repo.find_commit(target_oid)?;
+    repo.set_head_detached(commit.id())?;
+    let mut checkout_builder = git2::build::CheckoutBuilder::new();
+    checkout_builder.force();
+    repo.checkout_head(Some(&mut checkout_builder))?;
+
+    let grammar_dir = match &descriptor.subpath {
+        Some(subpath) => repo_path.join(subpath),
+        None => repo_path.clone(),
+    };
+
+    let grammar_path = grammar_dir.join("grammar.js");
     log::debug!("Grammar path is {}", grammar_path.display());
     if !grammar_path.exists() {
         log::debug!("Grammar path doesn't exist, this will probably not work");
@@ -160,41 +352,85 @@ pub fn generate_parser(lang: TargetLanguage) -> Result<()> {
     let grammar_path = grammar_path.as_os_str();
     let grammar_path_str = grammar_path.to_str().unwrap();
     generate::generate_parser_in_directory(
-        &repo_path,
+        &grammar_dir,
         Some(grammar_path_str),
         ABI_VERSION,
         true,
         None,
         None,
     )?;
-    log::trace!("generated parser for {:?}", lang);
+    log::trace!("generated parser for {:?}", descriptor.name);
 
     let mut loader = tree_sitter_loader::Loader::new().unwrap();
     loader.use_debug_build(false);
     loader.languages_at_path(&current_dir)?;
     // grammar path below is to git repo, not grammar.js/json
-    loader.compile_parser_at_path(&repo_path, std::path::PathBuf::from(sopath), &[""])?;
-    log::trace!("compiled parser for {:?}", lang);
+    loader.compile_parser_at_path(&grammar_dir, std::path::PathBuf::from(sopath), &[""])?;
+    log::trace!("compiled parser for {:?}", descriptor.name);
+
+    std::fs::write(&marker_path, target_oid.to_string())?;
 
     Ok(())
 }
 
-#[cfg(target_os = "linux")]
-fn check_for_parser(target_lang: TargetLanguage) -> Result<()> {
-    let home_path = match std::env::home_dir() {
-        Some(p) => p,
-        None => return Err(error::HtmlRenderingError::SharedLibDoesntExist.into()),
-    };
-    log::trace!("found home path {:?}", home_path);
+/// Generate a parser for the language by calling tree-sitter
+pub fn generate_parser(lang: TargetLanguage) -> Result<()> {
+    generate_parser_for(&lang.descriptor()?)
+}
 
-    let cache_path = home_path.join(".cache").join("tree-sitter").join("lib");
-    let soname = match target_lang.soname() {
-        Some(s) => s,
-        None => return Err(error::HtmlRenderingError::SharedLibDoesntExist.into()),
-    };
+/// Generate a parser for a custom grammar registered in a [`GrammarRegistry`]
+pub fn generate_parser_named(name: &str, registry: &GrammarRegistry) -> Result<()> {
+    generate_parser_for(&resolve_grammar(name, Some(registry))?)
+}
+
+/// Fetch and compile the parsers for several languages concurrently, using a bounded
+/// thread pool so first-run setup with multiple languages doesn't serialize one
+/// clone-and-compile after another. One failing grammar doesn't abort the rest; the
+/// per-grammar result is reported back to the caller instead.
+pub fn sync_grammars(langs: &[TargetLanguage]) -> Vec<(TargetLanguage, Result<()>)> {
+    let pool_size = std::thread::available_parallelism()
+        .map(std::num::NonZeroUsize::get)
+        .unwrap_or(4)
+        .min(langs.len().max(1));
+    let pool = threadpool::ThreadPool::new(pool_size);
+    let (tx, rx) = std::sync::mpsc::channel();
+
+    for &lang in langs {
+        let tx = tx.clone();
+        pool.execute(move || {
+            // Catch panics (e.g. an `unwrap()` deep in `generate_parser_for` tripping
+            // on an unexpected remote) so one bad grammar can't make its entry vanish
+            // from the result `Vec` instead of reporting an `Err`
+            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                generate_parser(lang)
+            }))
+            .unwrap_or_else(|payload| {
+                let message = payload
+                    .downcast_ref::<&str>()
+                    .map(|s| s.to_string())
+                    .or_else(|| payload.downcast_ref::<String>().cloned())
+                    .unwrap_or_else(|| "panicked while generating parser".to_string());
+                Err(anyhow::anyhow!(message))
+            });
+            tx.send((lang, result))
+                .expect("sync_grammars result channel should still be open");
+        });
+    }
+    drop(tx);
+
+    rx.into_iter().collect()
+}
+
+/// Fetch and compile the parsers for every built-in language
+pub fn sync_all() -> Vec<(TargetLanguage, Result<()>)> {
+    sync_grammars(TargetLanguage::ALL)
+}
+
+fn check_for_parser_descriptor(descriptor: &GrammarDescriptor) -> Result<()> {
+    let cache_path = cache_dir()?.join("lib");
     log::trace!("found cache path {:?}", cache_path);
 
-    let sopath = cache_path.join(soname);
+    let sopath = cache_path.join(format!("{}.{DYLIB_EXTENSION}", descriptor.soname));
     log::trace!("Looking for sofile {:?}", sopath);
 
     if !sopath.exists() {
@@ -206,6 +442,10 @@ fn check_for_parser(target_lang: TargetLanguage) -> Result<()> {
     }
 }
 
+fn check_for_parser(target_lang: TargetLanguage) -> Result<()> {
+    check_for_parser_descriptor(&target_lang.descriptor()?)
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -253,4 +493,66 @@ mod tests {
         super::check_for_parser(crate::TargetLanguage::Json).unwrap();
         super::check_for_parser(crate::TargetLanguage::Javascript).unwrap();
     }
+
+    #[test]
+    fn highlight_attr_inline_uses_theme_css() {
+        assert_eq!(
+            super::highlight_attr(true, Some("style=\"color: red\""), "keyword"),
+            "style=\"color: red\""
+        );
+        assert_eq!(super::highlight_attr(true, None, "keyword"), "");
+    }
+
+    #[test]
+    fn highlight_attr_class_ignores_theme_css() {
+        assert_eq!(
+            super::highlight_attr(false, Some("style=\"color: red\""), "keyword"),
+            "class=\"hl-keyword\""
+        );
+    }
+
+    #[test]
+    fn render_table_default_options_number_lines_from_one() {
+        let options = super::RenderOptions::default();
+        let mut out = String::new();
+        super::render_table(["fn main() {}"].into_iter(), &options, &mut out).unwrap();
+        assert!(out.contains("<td class=line-number>1</td>"));
+        assert!(out.contains("<td class=line>fn main() {}</td>"));
+    }
+
+    #[test]
+    fn render_table_can_hide_line_numbers() {
+        let options = super::RenderOptions {
+            show_line_numbers: false,
+            ..Default::default()
+        };
+        let mut out = String::new();
+        super::render_table(["a", "b"].into_iter(), &options, &mut out).unwrap();
+        assert!(!out.contains("line-number"));
+    }
+
+    #[test]
+    fn render_table_honors_custom_starting_line() {
+        let options = super::RenderOptions {
+            starting_line: 41,
+            ..Default::default()
+        };
+        let mut out = String::new();
+        super::render_table(["a", "b"].into_iter(), &options, &mut out).unwrap();
+        assert!(out.contains("<td class=line-number>41</td>"));
+        assert!(out.contains("<td class=line-number>42</td>"));
+    }
+
+    #[test]
+    fn render_table_marks_emphasized_lines() {
+        let options = super::RenderOptions {
+            emphasized_lines: std::collections::HashSet::from([2]),
+            ..Default::default()
+        };
+        let mut out = String::new();
+        super::render_table(["a", "b", "c"].into_iter(), &options, &mut out).unwrap();
+        assert!(out.contains("<tr class=emphasized-line><td class=line-number>2</td>"));
+        assert!(out.contains("<tr><td class=line-number>1</td>"));
+        assert!(out.contains("<tr><td class=line-number>3</td>"));
+    }
 }
@@ -0,0 +1,222 @@
+//! A TOML-driven registry of custom tree-sitter grammars, for languages beyond the
+//! built-in [`crate::TargetLanguage`] enum.
+//!
+//! Downstream users describe their own grammars in a `languages.toml`-style config:
+//!
+//! ```toml
+//! [[grammar]]
+//! name = "go"
+//! remote = "https://github.com/tree-sitter/tree-sitter-go.git"
+//! rev = "64457ea6b73ef5422ed1687178d4545c3e91334a"
+//! extensions = [".go"]
+//! soname = "go"
+//!
+//! [use-grammars]
+//! only = ["go"]
+//! ```
+
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+use anyhow::Result;
+
+/// A single grammar entry as it appears in the TOML config file
+#[derive(Debug, Clone, Deserialize)]
+struct GrammarEntry {
+    name: String,
+    remote: String,
+    rev: String,
+    subpath: Option<String>,
+    extensions: Vec<String>,
+    soname: String,
+}
+
+/// Restricts which of the config file's grammars are actually registered
+#[derive(Debug, Clone)]
+enum UseGrammars {
+    Only { only: Vec<String> },
+    Except { except: Vec<String> },
+}
+
+// A plain externally-tagged derive would require the documented
+// `[use-grammars]\nonly = [...]` to instead be nested as
+// `[use-grammars.only]\nonly = [...]`. Deserialize through a flat helper struct so the
+// `only`/`except` keys can live directly under `[use-grammars]`.
+impl<'de> Deserialize<'de> for UseGrammars {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct Raw {
+            only: Option<Vec<String>>,
+            except: Option<Vec<String>>,
+        }
+
+        let raw = Raw::deserialize(deserializer)?;
+        match (raw.only, raw.except) {
+            (Some(only), None) => Ok(Self::Only { only }),
+            (None, Some(except)) => Ok(Self::Except { except }),
+            (Some(_), Some(_)) => Err(serde::de::Error::custom(
+                "`use-grammars` cannot set both `only` and `except`",
+            )),
+            (None, None) => Err(serde::de::Error::custom(
+                "`use-grammars` must set either `only` or `except`",
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Default)]
+struct LanguagesConfig {
+    #[serde(default, rename = "grammar")]
+    grammars: Vec<GrammarEntry>,
+    #[serde(rename = "use-grammars")]
+    use_grammars: Option<UseGrammars>,
+}
+
+/// A fully resolved grammar descriptor: where to fetch it, which commit to pin, and
+/// what to call the compiled library. Produced either by
+/// [`crate::TargetLanguage::descriptor`] or by looking a name up in a [`GrammarRegistry`],
+/// so `generate_parser`/`check_for_parser` only ever need to deal with one shape.
+#[derive(Debug, Clone)]
+pub struct GrammarDescriptor {
+    pub name: String,
+    pub remote: String,
+    pub revision: Option<String>,
+    pub subpath: Option<String>,
+    pub soname: String,
+    pub extensions: Vec<String>,
+}
+
+/// A registry of custom grammars loaded from a `languages.toml`-style config, for
+/// highlighting languages that aren't in the built-in [`crate::TargetLanguage`] enum
+#[derive(Debug, Clone, Default)]
+pub struct GrammarRegistry {
+    grammars: HashMap<String, GrammarDescriptor>,
+}
+
+impl GrammarRegistry {
+    /// Load a registry from a TOML config file on disk
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)?;
+        Self::parse(&contents)
+    }
+
+    /// Parse a registry from a TOML config string
+    pub fn parse(contents: &str) -> Result<Self> {
+        let config: LanguagesConfig = toml::from_str(contents)?;
+
+        let is_selected: Box<dyn Fn(&str) -> bool> = match config.use_grammars {
+            Some(UseGrammars::Only { only }) => Box::new(move |name| only.iter().any(|n| n == name)),
+            Some(UseGrammars::Except { except }) => {
+                Box::new(move |name| !except.iter().any(|n| n == name))
+            }
+            None => Box::new(|_| true),
+        };
+
+        let grammars = config
+            .grammars
+            .into_iter()
+            .filter(|entry| is_selected(&entry.name))
+            .map(|entry| {
+                let descriptor = GrammarDescriptor {
+                    name: entry.name.clone(),
+                    remote: entry.remote,
+                    revision: Some(entry.rev),
+                    subpath: entry.subpath,
+                    soname: entry.soname,
+                    extensions: entry.extensions,
+                };
+                (entry.name, descriptor)
+            })
+            .collect();
+
+        Ok(Self { grammars })
+    }
+
+    /// Look up a registered grammar descriptor by name
+    pub fn resolve(&self, name: &str) -> Option<&GrammarDescriptor> {
+        self.grammars.get(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_module_doc_example() {
+        let registry = GrammarRegistry::parse(
+            r#"
+            [[grammar]]
+            name = "go"
+            remote = "https://github.com/tree-sitter/tree-sitter-go.git"
+            rev = "64457ea6b73ef5422ed1687178d4545c3e91334a"
+            extensions = [".go"]
+            soname = "go"
+
+            [use-grammars]
+            only = ["go"]
+            "#,
+        )
+        .unwrap();
+
+        let go = registry.resolve("go").expect("go should be registered");
+        assert_eq!(
+            go.remote,
+            "https://github.com/tree-sitter/tree-sitter-go.git"
+        );
+        assert_eq!(
+            go.revision.as_deref(),
+            Some("64457ea6b73ef5422ed1687178d4545c3e91334a")
+        );
+        assert_eq!(go.extensions, vec![".go".to_string()]);
+    }
+
+    const TWO_GRAMMARS: &str = r#"
+        [[grammar]]
+        name = "go"
+        remote = "https://example.com/go.git"
+        rev = "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa"
+        extensions = [".go"]
+        soname = "go"
+
+        [[grammar]]
+        name = "zig"
+        remote = "https://example.com/zig.git"
+        rev = "bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb"
+        extensions = [".zig"]
+        soname = "zig"
+    "#;
+
+    #[test]
+    fn use_grammars_only_keeps_listed_grammars() {
+        let registry =
+            GrammarRegistry::parse(&format!("{TWO_GRAMMARS}\n[use-grammars]\nonly = [\"go\"]\n"))
+                .unwrap();
+
+        assert!(registry.resolve("go").is_some());
+        assert!(registry.resolve("zig").is_none());
+    }
+
+    #[test]
+    fn use_grammars_except_drops_listed_grammars() {
+        let registry = GrammarRegistry::parse(&format!(
+            "{TWO_GRAMMARS}\n[use-grammars]\nexcept = [\"zig\"]\n"
+        ))
+        .unwrap();
+
+        assert!(registry.resolve("go").is_some());
+        assert!(registry.resolve("zig").is_none());
+    }
+
+    #[test]
+    fn no_use_grammars_keeps_everything() {
+        let registry = GrammarRegistry::parse(TWO_GRAMMARS).unwrap();
+
+        assert!(registry.resolve("go").is_some());
+        assert!(registry.resolve("zig").is_some());
+    }
+}
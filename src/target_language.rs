@@ -15,6 +15,18 @@ pub enum TargetLanguage {
 }
 
 impl TargetLanguage {
+    /// Every built-in language, for batch operations like [`crate::sync_all`]
+    pub const ALL: &'static [Self] = &[
+        Self::Rust,
+        Self::Python,
+        Self::Json,
+        Self::Yaml,
+        Self::Toml,
+        Self::Html,
+        Self::Javascript,
+        Self::Shell,
+    ];
+
     /// Retrieve the file extension associated with the language
     pub fn extension(&self) -> Option<&'static str> {
         match self {
@@ -39,32 +51,61 @@ impl TargetLanguage {
         }
     }
 
-    /// Get the name of the dynamic library associated with the language
+    /// Get the stem of the dynamic library associated with the language, without a platform
+    /// extension. Callers should compose the final filename with
+    /// [`crate::DYLIB_EXTENSION`]
     pub fn soname(&self) -> Option<&'static str> {
         match self {
-            Self::Rust => Some("rust.so"),
-            Self::Yaml => Some("yaml.so"),
-            Self::Python => Some("python.so"),
-            Self::Json => Some("json.so"),
-            Self::Javascript => Some("javascript.so"),
-            Self::Shell => Some("shell.so"),
-            Self::Html => Some("html.so"),
-            Self::Toml => Some("toml.so"),
+            Self::Rust => Some("rust"),
+            Self::Yaml => Some("yaml"),
+            Self::Python => Some("python"),
+            Self::Json => Some("json"),
+            Self::Javascript => Some("javascript"),
+            Self::Shell => Some("shell"),
+            Self::Html => Some("html"),
+            Self::Toml => Some("toml"),
         }
     }
 
     /// Find the git repository associated with the language
     pub fn git_repo(&self) -> Option<&'static str> {
-        match self {
-            Self::Rust => Some("https://github.com/tree-sitter/tree-sitter-rust.git"),
-            Self::Yaml => Some("https://github.com/tree-sitter-grammars/tree-sitter-yaml.git"),
-            Self::Python => Some("https://github.com/tree-sitter/tree-sitter-python.git"),
-            Self::Json => Some("https://github.com/tree-sitter/tree-sitter-json.git"),
-            Self::Javascript => Some("https://github.com/tree-sitter/tree-sitter-javascript.git"),
-            Self::Shell => Some("https://github.com/tree-sitter/tree-sitter-bash.git"),
-            Self::Html => Some("https://github.com/tree-sitter/tree-sitter-html.git"),
-            Self::Toml => Some("https://github.com/ikatyang/tree-sitter-toml.git"),
-        }
+        crate::builtin_grammars::lookup(&format!("{self:?}")).map(|(remote, ..)| remote)
+    }
+
+    /// Find the pinned commit SHA that `generate_parser` should check out for the language's
+    /// grammar repository, so builds are reproducible across upstream changes
+    pub fn revision(&self) -> Option<&'static str> {
+        crate::builtin_grammars::lookup(&format!("{self:?}")).map(|(_, revision, _)| revision)
+    }
+
+    /// Find the path, relative to the grammar's git repository root, that contains
+    /// `grammar.js`. Most grammar repositories keep it at the root, but multi-grammar
+    /// monorepos nest it under a subdirectory
+    pub fn subpath(&self) -> Option<&'static str> {
+        crate::builtin_grammars::lookup(&format!("{self:?}")).and_then(|(_, _, subpath)| subpath)
+    }
+
+    /// Resolve this built-in language into a generic [`crate::GrammarDescriptor`], so it
+    /// can be fetched and compiled by the same machinery that serves a
+    /// [`crate::GrammarRegistry`]'s custom grammars
+    pub fn descriptor(&self) -> anyhow::Result<crate::GrammarDescriptor> {
+        let remote = self
+            .git_repo()
+            .ok_or(crate::error::HtmlRenderingError::LanguageParserNotImplemented)?
+            .to_string();
+        let soname = self
+            .soname()
+            .ok_or(crate::error::HtmlRenderingError::SharedLibDoesntExist)?
+            .to_string();
+
+        Ok(crate::GrammarDescriptor {
+            name: format!("{self:?}").to_lowercase(),
+            remote,
+            revision: self.revision().map(str::to_string),
+            subpath: self.subpath().map(str::to_string),
+            soname,
+            extensions: self.extension().into_iter().map(str::to_string).collect(),
+        })
     }
 }
 